@@ -1,56 +1,245 @@
-/// A RISC-V simulator baed on
-/// ([the RISC-V Instruction Set Manual](https://riscv.org/specifications/),
-///  Volume 1, Version, 2.1, Section 2.4).
+//! A RISC-V simulator baed on
+//! ([the RISC-V Instruction Set Manual](https://riscv.org/specifications/),
+//!  Volume 1, Version, 2.1, Section 2.4).
 
-type Register = usize;
+pub type Register = usize;
 
-struct Processor {
+/// The calling-convention names of `x0` through `x31`.
+const ABI_NAMES: [&str; 32] = ["zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1",
+                               "a0", "a1", "a2", "a3", "a4", "a5", "a6", "a7", "s2", "s3",
+                               "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+                               "t5", "t6"];
+
+/// The ABI name of `reg` (e.g. `gp` for `x3`), for display.
+///
+/// Returns `None` for anything outside `x0` through `x31`.
+pub fn abi_name(reg: Register) -> Option<&'static str> {
+    ABI_NAMES.get(reg).cloned()
+}
+
+/// Look up a register by its ABI name (e.g. `a0`) or number (e.g. `x10`).
+///
+/// `fp` is accepted as an alias of `s0`.
+pub fn register_by_name(name: &str) -> Option<Register> {
+    if name == "fp" {
+        return Some(8);
+    }
+    if let Some(reg) = ABI_NAMES.iter().position(|&abi| abi == name) {
+        return Some(reg);
+    }
+    let digits = name.strip_prefix('x')?;
+    let canonical = digits == "0" || !digits.starts_with('0');
+    if !canonical || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    match digits.parse::<Register>() {
+        Ok(reg) if reg < 32 => Some(reg),
+        _ => None,
+    }
+}
+
+/// The inexact (NX) accrued exception flag in `fflags`.
+pub const FFLAG_INEXACT: u32 = 0x01;
+/// The invalid operation (NV) accrued exception flag in `fflags`.
+pub const FFLAG_INVALID: u32 = 0x10;
+
+/// The arithmetic that produced an [`ArithOverflow`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithOp {
+    Add,
+    Sub,
+    Addi,
+    /// Computing a jump or branch target address.
+    Jump,
+}
+
+/// A wrapped arithmetic result reported to the overflow hook.
+///
+/// `Add`, `Sub` and `Addi` report signed overflow; `Jump` reports the
+/// target address wrapping around the address space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArithOverflow {
+    pub pc: u32,
+    pub op: ArithOp,
+    pub lhs: u32,
+    pub rhs: u32,
+}
+
+type ArithOverflowHook = Box<dyn FnMut(&ArithOverflow) + Send>;
+
+pub struct Processor {
     // XXX make registers just 4 bytes that are interpreted as necessary,
     //     e.g. SLTIU wants things treated as unsigned.
     registers: [u32; 33], // registers[0] is unused; hard-wired to 0.
+    fregisters: [u32; 32], // Raw bits so NaN payloads are preserved.
+    fflags: u32,
+    pc: u32,
+    arith_overflow_hook: Option<ArithOverflowHook>,
 }
 
 impl Processor {
-    fn new() -> Processor {
-        Processor { registers: [0; 33] }
+    pub fn new() -> Processor {
+        Processor {
+            registers: [0; 33],
+            fregisters: [0; 32],
+            fflags: 0,
+            pc: 0,
+            arith_overflow_hook: None,
+        }
     }
 
-    fn get(&mut self, reg: Register) -> u32 {
+    /// Create a processor whose registers start out as garbage instead of 0.
+    ///
+    /// `xN` holds the upper 27 bits of `seed` with `N` in the lower 5 bits, so
+    /// every register except `x0` is non-zero and identifies itself. This
+    /// surfaces code that wrongly assumes registers are zeroed at entry.
+    pub fn with_poisoned_registers(seed: u32) -> Processor {
+        let mut cpu = Processor::new();
+        for reg in 1..32 {
+            cpu.set(reg, (seed & !0b011111) | reg as u32);
+        }
+        cpu
+    }
+
+    pub fn get(&self, reg: Register) -> u32 {
         match reg {
             0 => 0,
             _ => self.registers[reg],
         }
     }
 
-    fn set(&mut self, reg: Register, val: u32) {
+    pub fn set(&mut self, reg: Register, val: u32) {
         match reg {
             0 => (),  // No-op
             _ => self.registers[reg] = val,
         }
     }
 
+    /// Set registers by name (see `register_by_name()`).
+    ///
+    /// Nothing is written if any name is unknown. Writes to `zero`/`x0` are
+    /// ignored just like with `set()`.
+    pub fn set_registers(&mut self, map: &[(&str, u32)]) -> Result<(), String> {
+        let mut writes = Vec::with_capacity(map.len());
+        for &(name, val) in map {
+            match register_by_name(name) {
+                Some(reg) => writes.push((reg, val)),
+                None => return Err(format!("unknown register name: {}", name)),
+            }
+        }
+        for (reg, val) in writes {
+            self.set(reg, val);
+        }
+        Ok(())
+    }
+
+    /// Read `reg` reinterpreted as a two's complement signed value.
+    ///
+    /// The bits are unchanged, so `0x80000000` reads as `i32::MIN` and
+    /// `0xFFFFFFFF` reads as `-1`.
+    pub fn get_signed(&self, reg: Register) -> i32 {
+        self.get(reg) as i32
+    }
+
+    /// Write a signed value to `reg` as its two's complement bits.
+    ///
+    /// `i32::MIN` is stored as `0x80000000` and `-1` as `0xFFFFFFFF`.
+    /// Writes to `x0` are ignored just like with `set()`.
+    pub fn set_signed(&mut self, reg: Register, val: i32) {
+        self.set(reg, val as u32);
+    }
+
+    /// The address of the current instruction.
+    ///
+    /// Instruction methods only write `pc` when they transfer control (a
+    /// jump or a taken branch). The branch and jump methods return whether
+    /// they did so; when they return `false`, and after every other
+    /// instruction, moving on to the next sequential instruction is left to
+    /// the caller. A taken branch may leave `pc` unchanged (e.g.
+    /// `BEQ x0, x0, 0`), so compare the return value rather than `pc`.
+    pub fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    /// Set the address of the current instruction.
+    ///
+    /// As with `pc()`, this is the address of the instruction about to
+    /// execute; nothing advances it past instructions that do not transfer
+    /// control.
+    pub fn set_pc(&mut self, pc: u32) {
+        self.pc = pc;
+    }
+
+    /// Call `hook` every time arithmetic wraps.
+    ///
+    /// Wrapping is always the architectural behaviour; this is purely a
+    /// diagnostic for code that is not expected to overflow.
+    pub fn set_arith_overflow_hook<F>(&mut self, hook: F)
+        where F: FnMut(&ArithOverflow) + Send + 'static
+    {
+        self.arith_overflow_hook = Some(Box::new(hook));
+    }
+
+    fn report_overflow(&mut self, op: ArithOp, lhs: u32, rhs: u32) {
+        let event = ArithOverflow {
+            pc: self.pc,
+            op,
+            lhs,
+            rhs,
+        };
+        if let Some(ref mut hook) = self.arith_overflow_hook {
+            hook(&event);
+        }
+    }
+
+    /// Offset `base` by the sign-extended `imm` as a jump target.
+    fn jump_target(&mut self, base: u32, imm: u32) -> u32 {
+        let (target, overflow) = base.overflowing_add_signed(imm as i32);
+        if overflow {
+            self.report_overflow(ArithOp::Jump, base, imm);
+        }
+        target
+    }
+
+    pub fn get_float(&self, reg: Register) -> f32 {
+        f32::from_bits(self.fregisters[reg])
+    }
+
+    pub fn set_float(&mut self, reg: Register, val: f32) {
+        self.fregisters[reg] = val.to_bits();
+    }
+
+    /// The floating-point exception flags accrued so far.
+    pub fn fflags(&self) -> u32 {
+        self.fflags
+    }
+
     /// Add a sign-extended immediate to `rs1`.
     ///
-    /// Overflow is ignored.
+    /// The result wraps; signed overflow is reported to the hook set by
+    /// `set_arith_overflow_hook()`.
     /// `ADDI rd, rs1, 0` == `MV rd, rs1`
-    fn addi(&mut self, rd: Register, rs1: Register, imm: u32) {
+    pub fn addi(&mut self, rd: Register, rs1: Register, imm: u32) {
         let signed_imm = imm as i32;
-        let rs1_val = self.get(rs1) as i32;
-        let (result, _) = rs1_val.overflowing_add(signed_imm);
-        self.set(rd, result as u32);
+        let rs1_val = self.get_signed(rs1);
+        let (result, overflow) = rs1_val.overflowing_add(signed_imm);
+        if overflow {
+            self.report_overflow(ArithOp::Addi, rs1_val as u32, imm);
+        }
+        self.set_signed(rd, result);
     }
 
     /// Check if `rs1` is less than the sign-extended `imm`.
-    fn slti(&mut self, rd: Register, rs1: Register, imm: u32) {
+    pub fn slti(&mut self, rd: Register, rs1: Register, imm: u32) {
         let signed_imm = imm as i32;
-        let rs1_val = self.get(rs1) as i32;
+        let rs1_val = self.get_signed(rs1);
         self.set(rd, if rs1_val < signed_imm { 1 } else { 0 })
     }
 
     /// Check if `rs1` is less than sign-extended `imm` in an unsigned comparison.
     ///
     /// `SLTIU rd, rs1, 1` == `SEQZ rd, rs`
-    fn sltiu(&mut self, rd: Register, rs1: Register, imm: u32) {
+    pub fn sltiu(&mut self, rd: Register, rs1: Register, imm: u32) {
         let rs1_val: u32 = self.get(rs1);
         if imm == 1 {
             // SEQZ pseudo-op.
@@ -61,13 +250,13 @@ impl Processor {
     }
 
     /// Perform a bitwise AND against `imm`.
-    fn andi(&mut self, rd: Register, rs1: Register, imm: u32) {
+    pub fn andi(&mut self, rd: Register, rs1: Register, imm: u32) {
         let rs1_val = self.get(rs1);
         self.set(rd, rs1_val & imm);
     }
 
     /// Perform a bitwise OR against `imm`.
-    fn ori(&mut self, rd: Register, rs1: Register, imm: u32) {
+    pub fn ori(&mut self, rd: Register, rs1: Register, imm: u32) {
         let rs1_val = self.get(rs1);
         self.set(rd, rs1_val | imm);
     }
@@ -75,12 +264,450 @@ impl Processor {
     /// Perform a bitwise XOR against `imm`.
     ///
     /// `XORI rd, sr1, -1` == `NOT rd, rs`
-    fn xori(&mut self, rd: Register, rs1: Register, imm: u32) {
+    pub fn xori(&mut self, rd: Register, rs1: Register, imm: u32) {
         let rs1_val = self.get(rs1);
         self.set(rd, rs1_val ^ imm);
     }
+
+    /// Add `rs1` to `rs2`.
+    ///
+    /// The result wraps; signed overflow is reported to the hook set by
+    /// `set_arith_overflow_hook()`.
+    pub fn add(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let (rs1_val, rs2_val) = (self.get_signed(rs1), self.get_signed(rs2));
+        let (result, overflow) = rs1_val.overflowing_add(rs2_val);
+        if overflow {
+            self.report_overflow(ArithOp::Add, rs1_val as u32, rs2_val as u32);
+        }
+        self.set_signed(rd, result);
+    }
+
+    /// Subtract `rs2` from `rs1`.
+    ///
+    /// The result wraps; signed overflow is reported to the hook set by
+    /// `set_arith_overflow_hook()`.
+    /// `SUB rd, x0, rs` == `NEG rd, rs`
+    pub fn sub(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let (rs1_val, rs2_val) = (self.get_signed(rs1), self.get_signed(rs2));
+        let (result, overflow) = rs1_val.overflowing_sub(rs2_val);
+        if overflow {
+            self.report_overflow(ArithOp::Sub, rs1_val as u32, rs2_val as u32);
+        }
+        self.set_signed(rd, result);
+    }
+
+    /// Shift `rs1` left logically by the lower 5 bits of `rs2`.
+    pub fn sll(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let shamt = self.get(rs2) & 0b011111;
+        let result = self.get(rs1) << shamt;
+        self.set(rd, result);
+    }
+
+    /// Check if `rs1` is less than `rs2`.
+    ///
+    /// `SLT rd, rs, x0` == `SLTZ rd, rs`
+    /// `SLT rd, x0, rs` == `SGTZ rd, rs`
+    pub fn slt(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get_signed(rs1) < self.get_signed(rs2);
+        self.set(rd, if result { 1 } else { 0 });
+    }
+
+    /// Check if `rs1` is less than `rs2` in an unsigned comparison.
+    ///
+    /// `SLTU rd, x0, rs` == `SNEZ rd, rs`
+    pub fn sltu(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get(rs1) < self.get(rs2);
+        self.set(rd, if result { 1 } else { 0 });
+    }
+
+    /// Perform a bitwise XOR of `rs1` against `rs2`.
+    pub fn xor(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get(rs1) ^ self.get(rs2);
+        self.set(rd, result);
+    }
+
+    /// Shift `rs1` right logically by the lower 5 bits of `rs2`.
+    pub fn srl(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let shamt = self.get(rs2) & 0b011111;
+        let result = self.get(rs1) >> shamt;
+        self.set(rd, result);
+    }
+
+    /// Shift `rs1` right arithmetically by the lower 5 bits of `rs2`.
+    pub fn sra(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let shamt = self.get(rs2) & 0b011111;
+        let result = self.get_signed(rs1) >> shamt;
+        self.set_signed(rd, result);
+    }
+
+    /// Perform a bitwise OR of `rs1` against `rs2`.
+    pub fn or(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get(rs1) | self.get(rs2);
+        self.set(rd, result);
+    }
+
+    /// Perform a bitwise AND of `rs1` against `rs2`.
+    pub fn and(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get(rs1) & self.get(rs2);
+        self.set(rd, result);
+    }
+
+    /// Jump by the sign-extended `imm`, storing the return address in `rd`.
+    ///
+    /// `JAL x0, offset` == `J offset`
+    /// `JAL x1, offset` == `JAL offset`
+    ///
+    /// Always transfers control, so always returns `true`.
+    pub fn jal(&mut self, rd: Register, imm: u32) -> bool {
+        let return_addr = self.pc.wrapping_add(4);
+        let pc = self.pc;
+        self.pc = self.jump_target(pc, imm);
+        self.set(rd, return_addr);
+        true
+    }
+
+    /// Jump to `rs1` plus the sign-extended `imm` with the lowest bit
+    /// cleared, storing the return address in `rd`.
+    ///
+    /// `JALR x0, rs, 0` == `JR rs`
+    /// `JALR x0, x1, 0` == `RET`
+    ///
+    /// Always transfers control, so always returns `true`.
+    pub fn jalr(&mut self, rd: Register, rs1: Register, imm: u32) -> bool {
+        let return_addr = self.pc.wrapping_add(4);
+        let rs1_val = self.get(rs1);
+        self.pc = self.jump_target(rs1_val, imm) & !1;
+        self.set(rd, return_addr);
+        true
+    }
+
+    /// Branch by the sign-extended `imm` if `taken`, else leave `pc` alone.
+    ///
+    /// Returns `taken`.
+    fn branch(&mut self, taken: bool, imm: u32) -> bool {
+        if taken {
+            let pc = self.pc;
+            self.pc = self.jump_target(pc, imm);
+        }
+        taken
+    }
+
+    /// Branch if `rs1` and `rs2` are equal.
+    ///
+    /// `BEQ rs, x0, offset` == `BEQZ rs, offset`
+    pub fn beq(&mut self, rs1: Register, rs2: Register, imm: u32) -> bool {
+        let taken = self.get(rs1) == self.get(rs2);
+        self.branch(taken, imm)
+    }
+
+    /// Branch if `rs1` and `rs2` are not equal.
+    ///
+    /// `BNE rs, x0, offset` == `BNEZ rs, offset`
+    pub fn bne(&mut self, rs1: Register, rs2: Register, imm: u32) -> bool {
+        let taken = self.get(rs1) != self.get(rs2);
+        self.branch(taken, imm)
+    }
+
+    /// Branch if `rs1` is less than `rs2`.
+    ///
+    /// `BLT rs, x0, offset` == `BLTZ rs, offset`
+    pub fn blt(&mut self, rs1: Register, rs2: Register, imm: u32) -> bool {
+        let taken = self.get_signed(rs1) < self.get_signed(rs2);
+        self.branch(taken, imm)
+    }
+
+    /// Branch if `rs1` is greater than or equal to `rs2`.
+    ///
+    /// `BGE rs, x0, offset` == `BGEZ rs, offset`
+    pub fn bge(&mut self, rs1: Register, rs2: Register, imm: u32) -> bool {
+        let taken = self.get_signed(rs1) >= self.get_signed(rs2);
+        self.branch(taken, imm)
+    }
+
+    /// Branch if `rs1` is less than `rs2` in an unsigned comparison.
+    pub fn bltu(&mut self, rs1: Register, rs2: Register, imm: u32) -> bool {
+        let taken = self.get(rs1) < self.get(rs2);
+        self.branch(taken, imm)
+    }
+
+    /// Branch if `rs1` is greater than or equal to `rs2` in an unsigned
+    /// comparison.
+    pub fn bgeu(&mut self, rs1: Register, rs2: Register, imm: u32) -> bool {
+        let taken = self.get(rs1) >= self.get(rs2);
+        self.branch(taken, imm)
+    }
+
+    /// Add `rs2` to `rs1` shifted left by one bit (Zba).
+    ///
+    /// Overflow is ignored.
+    pub fn sh1add(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        self.shnadd(rd, rs1, rs2, 1);
+    }
+
+    /// Add `rs2` to `rs1` shifted left by two bits (Zba).
+    ///
+    /// Overflow is ignored.
+    pub fn sh2add(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        self.shnadd(rd, rs1, rs2, 2);
+    }
+
+    /// Add `rs2` to `rs1` shifted left by three bits (Zba).
+    ///
+    /// Overflow is ignored.
+    pub fn sh3add(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        self.shnadd(rd, rs1, rs2, 3);
+    }
+
+    fn shnadd(&mut self, rd: Register, rs1: Register, rs2: Register, shamt: u32) {
+        let shifted = self.get(rs1) << shamt;
+        let (result, _) = shifted.overflowing_add(self.get(rs2));
+        self.set(rd, result);
+    }
+
+    /// Perform a bitwise AND of `rs1` against the inverse of `rs2` (Zbb).
+    pub fn andn(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get(rs1) & !self.get(rs2);
+        self.set(rd, result);
+    }
+
+    /// Perform a bitwise OR of `rs1` against the inverse of `rs2` (Zbb).
+    pub fn orn(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get(rs1) | !self.get(rs2);
+        self.set(rd, result);
+    }
+
+    /// Perform a bitwise exclusive NOR of `rs1` and `rs2` (Zbb).
+    pub fn xnor(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = !(self.get(rs1) ^ self.get(rs2));
+        self.set(rd, result);
+    }
+
+    /// Count the leading zero bits of `rs1` (Zbb).
+    ///
+    /// A value of 0 has 32 leading zeros.
+    pub fn clz(&mut self, rd: Register, rs1: Register) {
+        let result = self.get(rs1).leading_zeros();
+        self.set(rd, result);
+    }
+
+    /// Count the trailing zero bits of `rs1` (Zbb).
+    ///
+    /// A value of 0 has 32 trailing zeros.
+    pub fn ctz(&mut self, rd: Register, rs1: Register) {
+        let result = self.get(rs1).trailing_zeros();
+        self.set(rd, result);
+    }
+
+    /// Count the set bits of `rs1` (Zbb).
+    pub fn cpop(&mut self, rd: Register, rs1: Register) {
+        let result = self.get(rs1).count_ones();
+        self.set(rd, result);
+    }
+
+    /// Select the lesser of `rs1` and `rs2` in a signed comparison (Zbb).
+    pub fn min(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get_signed(rs1).min(self.get_signed(rs2));
+        self.set_signed(rd, result);
+    }
+
+    /// Select the greater of `rs1` and `rs2` in a signed comparison (Zbb).
+    pub fn max(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get_signed(rs1).max(self.get_signed(rs2));
+        self.set_signed(rd, result);
+    }
+
+    /// Select the lesser of `rs1` and `rs2` in an unsigned comparison (Zbb).
+    pub fn minu(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get(rs1).min(self.get(rs2));
+        self.set(rd, result);
+    }
+
+    /// Select the greater of `rs1` and `rs2` in an unsigned comparison (Zbb).
+    pub fn maxu(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get(rs1).max(self.get(rs2));
+        self.set(rd, result);
+    }
+
+    /// Sign-extend the least-significant byte of `rs1` (Zbb).
+    pub fn sext_b(&mut self, rd: Register, rs1: Register) {
+        let result = self.get(rs1) as u8 as i8 as i32;
+        self.set_signed(rd, result);
+    }
+
+    /// Sign-extend the least-significant halfword of `rs1` (Zbb).
+    pub fn sext_h(&mut self, rd: Register, rs1: Register) {
+        let result = self.get(rs1) as u16 as i16 as i32;
+        self.set_signed(rd, result);
+    }
+
+    /// Zero-extend the least-significant halfword of `rs1` (Zbb).
+    pub fn zext_h(&mut self, rd: Register, rs1: Register) {
+        let result = self.get(rs1) & 0xffff;
+        self.set(rd, result);
+    }
+
+    /// Rotate `rs1` left by the lower 5 bits of `rs2` (Zbb).
+    pub fn rol(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get(rs1).rotate_left(self.get(rs2) & 0b011111);
+        self.set(rd, result);
+    }
+
+    /// Rotate `rs1` right by the lower 5 bits of `rs2` (Zbb).
+    pub fn ror(&mut self, rd: Register, rs1: Register, rs2: Register) {
+        let result = self.get(rs1).rotate_right(self.get(rs2) & 0b011111);
+        self.set(rd, result);
+    }
+
+    /// Rotate `rs1` right by the lower 5 bits of `imm` (Zbb).
+    pub fn rori(&mut self, rd: Register, rs1: Register, imm: u32) {
+        let result = self.get(rs1).rotate_right(imm & 0b011111);
+        self.set(rd, result);
+    }
+
+    /// Reverse the order of the bytes in `rs1` (Zbb).
+    pub fn rev8(&mut self, rd: Register, rs1: Register) {
+        let result = self.get(rs1).swap_bytes();
+        self.set(rd, result);
+    }
+
+    /// Set each byte to all ones if any of its bits are set, else zero (Zbb).
+    pub fn orc_b(&mut self, rd: Register, rs1: Register) {
+        let rs1_val = self.get(rs1);
+        let mut result = 0;
+        for byte in 0..4 {
+            if rs1_val & (0xff << (byte * 8)) != 0 {
+                result |= 0xff << (byte * 8);
+            }
+        }
+        self.set(rd, result);
+    }
+
+    /// Classify the float in `rs1`, setting exactly one bit in `rd` (F).
+    ///
+    /// From bit 0 upward: -inf, negative normal, negative subnormal, -0,
+    /// +0, positive subnormal, positive normal, +inf, signaling NaN, and
+    /// quiet NaN.
+    pub fn fclass_s(&mut self, rd: Register, rs1: Register) {
+        let bits = self.fregisters[rs1];
+        let negative = bits >> 31 == 1;
+        let exponent = (bits >> 23) & 0xff;
+        let fraction = bits & 0x007fffff;
+        let class = match (exponent, fraction) {
+            (0xff, 0) if negative => 0,
+            (0xff, 0) => 7,
+            (0xff, _) if fraction & 0x00400000 == 0 => 8,
+            (0xff, _) => 9,
+            (0, 0) if negative => 3,
+            (0, 0) => 4,
+            (0, _) if negative => 2,
+            (0, _) => 5,
+            _ if negative => 1,
+            _ => 6,
+        };
+        self.set(rd, 1 << class);
+    }
+
+    /// Convert the float in `rs1` to a signed integer (F).
+    ///
+    /// Rounds to nearest, ties to even. NaN and values too large saturate
+    /// to `i32::MAX`, values too small to `i32::MIN`, and both raise the
+    /// invalid flag.
+    pub fn fcvt_w_s(&mut self, rd: Register, rs1: Register) {
+        let val = self.get_float(rs1);
+        let rounded = val.round_ties_even();
+        let result = if val.is_nan() || rounded >= 2147483648.0 {
+            self.fflags |= FFLAG_INVALID;
+            i32::MAX
+        } else if rounded < -2147483648.0 {
+            self.fflags |= FFLAG_INVALID;
+            i32::MIN
+        } else {
+            if rounded != val {
+                self.fflags |= FFLAG_INEXACT;
+            }
+            rounded as i32
+        };
+        self.set_signed(rd, result);
+    }
+
+    /// Convert the float in `rs1` to an unsigned integer (F).
+    ///
+    /// Rounds to nearest, ties to even. NaN and values too large saturate
+    /// to `u32::MAX`, negative values to 0, and both raise the invalid
+    /// flag.
+    pub fn fcvt_wu_s(&mut self, rd: Register, rs1: Register) {
+        let val = self.get_float(rs1);
+        let rounded = val.round_ties_even();
+        let result = if val.is_nan() || rounded >= 4294967296.0 {
+            self.fflags |= FFLAG_INVALID;
+            u32::MAX
+        } else if rounded < 0.0 {
+            self.fflags |= FFLAG_INVALID;
+            0
+        } else {
+            if rounded != val {
+                self.fflags |= FFLAG_INEXACT;
+            }
+            rounded as u32
+        };
+        self.set(rd, result);
+    }
+
+    /// Convert the signed integer in `rs1` to a float (F).
+    ///
+    /// Rounds to nearest, ties to even, raising the inexact flag when
+    /// precision is lost.
+    pub fn fcvt_s_w(&mut self, rd: Register, rs1: Register) {
+        let val = self.get_signed(rs1);
+        let result = val as f32;
+        if f64::from(result) != f64::from(val) {
+            self.fflags |= FFLAG_INEXACT;
+        }
+        self.set_float(rd, result);
+    }
+
+    /// Convert the unsigned integer in `rs1` to a float (F).
+    ///
+    /// Rounds to nearest, ties to even, raising the inexact flag when
+    /// precision is lost.
+    pub fn fcvt_s_wu(&mut self, rd: Register, rs1: Register) {
+        let val = self.get(rs1);
+        let result = val as f32;
+        if f64::from(result) != f64::from(val) {
+            self.fflags |= FFLAG_INEXACT;
+        }
+        self.set_float(rd, result);
+    }
+}
+
+impl Default for Processor {
+    fn default() -> Processor {
+        Processor::new()
+    }
+}
+
+/// Determine the length in bytes of the instruction starting with `parcel`,
+/// the lowest 16 bits of the instruction
+/// (Volume 1, Version 2.1, Section 1.2).
+///
+/// Compressed instructions are 2 bytes and standard ones 4 bytes. The longer
+/// encodings are 6, 8, or 10 + 2 * `nnn` bytes (`nnn` being bits [14:12]),
+/// with the reserved 192+ bit encoding reported as 24 bytes.
+pub fn instruction_length(parcel: u16) -> u8 {
+    if parcel & 0b11 != 0b11 {
+        2
+    } else if parcel & 0b11100 != 0b11100 {
+        4
+    } else if parcel & 0b0111111 == 0b0011111 {
+        6
+    } else if parcel & 0b1111111 == 0b0111111 {
+        8
+    } else {
+        10 + 2 * ((parcel >> 12) & 0b111) as u8
+    }
 }
 
+#[cfg(test)]
 fn sign_extend(imm: u32) -> u32 {
     // From https://github.com/riscv/riscv-tests/blob/master/isa/macros/scalar/test_macros.h
     let signed_imm = imm as i32;
@@ -88,6 +715,7 @@ fn sign_extend(imm: u32) -> u32 {
     extended_imm as u32
 }
 
+#[cfg(test)]
 macro_rules! test_imm_op {
     ($test_num: expr, $inst:ident, $result:expr, $val1:expr, $imm:expr) => {{
         let mut cpu = Processor::new();
@@ -99,6 +727,7 @@ macro_rules! test_imm_op {
     }};
 }
 
+#[cfg(test)]
 macro_rules! test_imm_src1_eq_dest {
     ($test_num:expr, $inst:ident, $result:expr, $val1:expr, $imm:expr) => {{
         let mut cpu = Processor::new();
@@ -110,6 +739,7 @@ macro_rules! test_imm_src1_eq_dest {
     }}
 }
 
+#[cfg(test)]
 macro_rules! test_imm_zerosrc1 {
     ($test_num:expr, $inst:ident, $result:expr, $imm:expr) => {{
         let mut cpu = Processor::new();
@@ -120,6 +750,7 @@ macro_rules! test_imm_zerosrc1 {
     }}
 }
 
+#[cfg(test)]
 macro_rules! test_imm_zerodest {
     ($test_num:expr, $inst:ident, $val1:expr, $imm:expr) => {{
         let mut cpu = Processor::new();
@@ -130,6 +761,211 @@ macro_rules! test_imm_zerodest {
     }}
 }
 
+#[cfg(test)]
+macro_rules! test_rr_op {
+    ($test_num:expr, $inst:ident, $result:expr, $val1:expr, $val2:expr) => {{
+        let mut cpu = Processor::new();
+        let rd: Register = 3;
+        let rs1: Register = 1;
+        let rs2: Register = 2;
+        cpu.set(rs1, $val1);
+        cpu.set(rs2, $val2);
+        cpu.$inst(rd, rs1, rs2);
+        assert_eq!($result, cpu.get(rd));
+    }};
+}
+
+#[cfg(test)]
+macro_rules! test_rr_src1_eq_dest {
+    ($test_num:expr, $inst:ident, $result:expr, $val1:expr, $val2:expr) => {{
+        let mut cpu = Processor::new();
+        let rd: Register = 1;
+        let rs1: Register = 1;
+        let rs2: Register = 2;
+        cpu.set(rs1, $val1);
+        cpu.set(rs2, $val2);
+        cpu.$inst(rd, rs1, rs2);
+        assert_eq!($result, cpu.get(rd));
+    }};
+}
+
+#[cfg(test)]
+macro_rules! test_rr_src2_eq_dest {
+    ($test_num:expr, $inst:ident, $result:expr, $val1:expr, $val2:expr) => {{
+        let mut cpu = Processor::new();
+        let rd: Register = 2;
+        let rs1: Register = 1;
+        let rs2: Register = 2;
+        cpu.set(rs1, $val1);
+        cpu.set(rs2, $val2);
+        cpu.$inst(rd, rs1, rs2);
+        assert_eq!($result, cpu.get(rd));
+    }};
+}
+
+#[cfg(test)]
+macro_rules! test_rr_src12_eq_dest {
+    ($test_num:expr, $inst:ident, $result:expr, $val1:expr) => {{
+        let mut cpu = Processor::new();
+        let rd: Register = 1;
+        let rs1: Register = 1;
+        cpu.set(rs1, $val1);
+        cpu.$inst(rd, rs1, rs1);
+        assert_eq!($result, cpu.get(rd));
+    }};
+}
+
+#[cfg(test)]
+macro_rules! test_rr_zerosrc1 {
+    ($test_num:expr, $inst:ident, $result:expr, $val:expr) => {{
+        let mut cpu = Processor::new();
+        let rd: Register = 3;
+        let rs1: Register = 0;
+        let rs2: Register = 2;
+        cpu.set(rs2, $val);
+        cpu.$inst(rd, rs1, rs2);
+        assert_eq!($result, cpu.get(rd));
+    }};
+}
+
+#[cfg(test)]
+macro_rules! test_rr_zerosrc2 {
+    ($test_num:expr, $inst:ident, $result:expr, $val:expr) => {{
+        let mut cpu = Processor::new();
+        let rd: Register = 3;
+        let rs1: Register = 1;
+        let rs2: Register = 0;
+        cpu.set(rs1, $val);
+        cpu.$inst(rd, rs1, rs2);
+        assert_eq!($result, cpu.get(rd));
+    }};
+}
+
+#[cfg(test)]
+macro_rules! test_rr_zerosrc12 {
+    ($test_num:expr, $inst:ident, $result:expr) => {{
+        let mut cpu = Processor::new();
+        let rd: Register = 3;
+        cpu.$inst(rd, 0, 0);
+        assert_eq!($result, cpu.get(rd));
+    }};
+}
+
+#[cfg(test)]
+macro_rules! test_rr_zerodest {
+    ($test_num:expr, $inst:ident, $val1:expr, $val2:expr) => {{
+        let mut cpu = Processor::new();
+        let rd: Register = 0;
+        let rs1: Register = 1;
+        let rs2: Register = 2;
+        cpu.set(rs1, $val1);
+        cpu.set(rs2, $val2);
+        cpu.$inst(rd, rs1, rs2);
+        assert_eq!(0, cpu.get(rd));
+    }};
+}
+
+#[cfg(test)]
+macro_rules! test_r_op {
+    ($test_num:expr, $inst:ident, $result:expr, $val1:expr) => {{
+        let mut cpu = Processor::new();
+        let rd: Register = 3;
+        let rs1: Register = 1;
+        cpu.set(rs1, $val1);
+        cpu.$inst(rd, rs1);
+        assert_eq!($result, cpu.get(rd));
+    }};
+}
+
+#[cfg(test)]
+macro_rules! test_fp_int_op {
+    ($test_num:expr, $inst:ident, $flags:expr, $result:expr, $val1:expr) => {{
+        let mut cpu = Processor::new();
+        let rd: Register = 3;
+        let rs1: Register = 1;
+        cpu.set_float(rs1, $val1);
+        cpu.$inst(rd, rs1);
+        assert_eq!($result, cpu.get(rd));
+        assert_eq!($flags, cpu.fflags());
+    }};
+}
+
+#[cfg(test)]
+macro_rules! test_int_fp_op {
+    ($test_num:expr, $inst:ident, $flags:expr, $result:expr, $val1:expr) => {{
+        let mut cpu = Processor::new();
+        let rd: Register = 3;
+        let rs1: Register = 1;
+        cpu.set(rs1, $val1);
+        cpu.$inst(rd, rs1);
+        assert_eq!(($result as f32).to_bits(), cpu.get_float(rd).to_bits());
+        assert_eq!($flags, cpu.fflags());
+    }};
+}
+
+#[cfg(test)]
+macro_rules! test_br_op {
+    ($test_num:expr, $inst:ident, $taken:expr, $val1:expr, $val2:expr) => {{
+        let mut cpu = Processor::new();
+        let rs1: Register = 1;
+        let rs2: Register = 2;
+        cpu.set(rs1, $val1);
+        cpu.set(rs2, $val2);
+        cpu.set_pc(0x1000);
+        assert_eq!($taken, cpu.$inst(rs1, rs2, 0x10));
+        assert_eq!(if $taken { 0x1010 } else { 0x1000 }, cpu.pc());
+    }};
+}
+
+/// Assert several registers at once, reporting every mismatch together.
+///
+/// An optional `pc => value` may come first to check the pc as well.
+#[cfg(test)]
+macro_rules! assert_state {
+    (@check $cpu:expr, $pc:expr, $($reg:expr => $val:expr),*) => {{
+        let mut mismatches = Vec::new();
+        if let Some(expected) = $pc {
+            let found = $cpu.pc();
+            if found != expected {
+                mismatches.push(format!("pc: expected {:#010x}, found {:#010x}",
+                                        expected, found));
+            }
+        }
+        $(
+            let found = $cpu.get($reg);
+            if found != $val {
+                mismatches.push(format!("x{}: expected {:#010x}, found {:#010x}",
+                                        $reg, $val, found));
+            }
+        )*
+        if !mismatches.is_empty() {
+            panic!("register state mismatch:\n{}", mismatches.join("\n"));
+        }
+    }};
+    ($cpu:expr, pc => $pc:expr $(, $reg:expr => $val:expr)* $(,)?) => {
+        assert_state!(@check $cpu, Some($pc), $($reg => $val),*)
+    };
+    ($cpu:expr, $($reg:expr => $val:expr),+ $(,)?) => {
+        assert_state!(@check $cpu, None::<u32>, $($reg => $val),+)
+    };
+}
+
+#[test]
+fn signed_registers() {
+    let mut cpu = Processor::new();
+    cpu.set_signed(1, -1);
+    assert_eq!(0xffffffff, cpu.get(1));
+    assert_eq!(-1, cpu.get_signed(1));
+
+    cpu.set(2, 0x80000000);
+    assert_eq!(i32::MIN, cpu.get_signed(2));
+    cpu.set_signed(2, i32::MIN);
+    assert_eq!(0x80000000, cpu.get(2));
+
+    cpu.set_signed(0, -1);
+    assert_eq!(0, cpu.get_signed(0));
+}
+
 #[test]
 fn addi() {
     // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/addi.S
@@ -264,3 +1100,737 @@ fn xori() {
     test_imm_zerosrc1!(13, xori, 0x0f0, 0x0f0);
     test_imm_zerodest!(14, xori, 0x00ff00ff, 0x70f);
 }
+
+#[test]
+fn add() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/add.S
+    test_rr_op!(2, add, 0x00000000, 0x00000000, 0x00000000);
+    test_rr_op!(3, add, 0x00000002, 0x00000001, 0x00000001);
+    test_rr_op!(4, add, 0x0000000a, 0x00000003, 0x00000007);
+    test_rr_op!(5, add, 0xffff8000, 0x00000000, 0xffff8000);
+    test_rr_op!(6, add, 0x80000000, 0x80000000, 0x00000000);
+    test_rr_op!(7, add, 0x7fff8000, 0x80000000, 0xffff8000);
+    test_rr_op!(8, add, 0x00007fff, 0x00000000, 0x00007fff);
+    test_rr_op!(9, add, 0x7fffffff, 0x7fffffff, 0x00000000);
+    test_rr_op!(10, add, 0x80007ffe, 0x7fffffff, 0x00007fff);
+    test_rr_op!(11, add, 0x80007fff, 0x80000000, 0x00007fff);
+    test_rr_op!(12, add, 0x7fff7fff, 0x7fffffff, 0xffff8000);
+    test_rr_op!(13, add, 0xffffffff, 0x00000000, 0xffffffff);
+    test_rr_op!(14, add, 0x00000000, 0xffffffff, 0x00000001);
+    test_rr_op!(15, add, 0xfffffffe, 0xffffffff, 0xffffffff);
+    test_rr_op!(16, add, 0x80000000, 0x00000001, 0x7fffffff);
+
+    test_rr_src1_eq_dest!(17, add, 24, 13, 11);
+    test_rr_src2_eq_dest!(18, add, 25, 14, 11);
+    test_rr_src12_eq_dest!(19, add, 26, 13);
+
+    test_rr_zerosrc1!(35, add, 15, 15);
+    test_rr_zerosrc2!(36, add, 32, 32);
+    test_rr_zerosrc12!(37, add, 0);
+    test_rr_zerodest!(38, add, 16, 30);
+}
+
+#[test]
+fn sub() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/sub.S
+    test_rr_op!(2, sub, 0x00000000, 0x00000000, 0x00000000);
+    test_rr_op!(3, sub, 0x00000000, 0x00000001, 0x00000001);
+    test_rr_op!(4, sub, 0xfffffffc, 0x00000003, 0x00000007);
+    test_rr_op!(5, sub, 0x00008000, 0x00000000, 0xffff8000);
+    test_rr_op!(6, sub, 0x80000000, 0x80000000, 0x00000000);
+    test_rr_op!(7, sub, 0x80008000, 0x80000000, 0xffff8000);
+    test_rr_op!(8, sub, 0xffff8001, 0x00000000, 0x00007fff);
+    test_rr_op!(9, sub, 0x7fffffff, 0x7fffffff, 0x00000000);
+    test_rr_op!(10, sub, 0x7fff8000, 0x7fffffff, 0x00007fff);
+    test_rr_op!(11, sub, 0x7fff8001, 0x80000000, 0x00007fff);
+    test_rr_op!(12, sub, 0x80007fff, 0x7fffffff, 0xffff8000);
+    test_rr_op!(13, sub, 0x00000001, 0x00000000, 0xffffffff);
+    test_rr_op!(14, sub, 0xfffffffe, 0xffffffff, 0x00000001);
+    test_rr_op!(15, sub, 0x00000000, 0xffffffff, 0xffffffff);
+
+    test_rr_src1_eq_dest!(16, sub, 2, 13, 11);
+    test_rr_src2_eq_dest!(17, sub, 3, 14, 11);
+    test_rr_src12_eq_dest!(18, sub, 0, 13);
+
+    test_rr_zerosrc1!(34, sub, 15, 0xfffffff1);
+    test_rr_zerosrc2!(35, sub, 32, 32);
+    test_rr_zerosrc12!(36, sub, 0);
+    test_rr_zerodest!(37, sub, 16, 30);
+}
+
+#[test]
+fn sll() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/sll.S
+    test_rr_op!(2, sll, 0x00000001, 0x00000001, 0);
+    test_rr_op!(3, sll, 0x00000002, 0x00000001, 1);
+    test_rr_op!(4, sll, 0x00000080, 0x00000001, 7);
+    test_rr_op!(5, sll, 0x00004000, 0x00000001, 14);
+    test_rr_op!(6, sll, 0x80000000, 0x00000001, 31);
+    test_rr_op!(7, sll, 0xffffffff, 0xffffffff, 0);
+    test_rr_op!(8, sll, 0xfffffffe, 0xffffffff, 1);
+    test_rr_op!(9, sll, 0xffffff80, 0xffffffff, 7);
+    test_rr_op!(10, sll, 0xffffc000, 0xffffffff, 14);
+    test_rr_op!(11, sll, 0x80000000, 0xffffffff, 31);
+    test_rr_op!(12, sll, 0x21212121, 0x21212121, 0);
+    test_rr_op!(13, sll, 0x42424242, 0x21212121, 1);
+    test_rr_op!(14, sll, 0x90909080, 0x21212121, 7);
+    test_rr_op!(15, sll, 0x48484000, 0x21212121, 14);
+    test_rr_op!(16, sll, 0x80000000, 0x21212121, 31);
+    test_rr_op!(17, sll, 0x21212121, 0x21212121, 0xffffffc0);
+    test_rr_op!(18, sll, 0x42424242, 0x21212121, 0xffffffc1);
+    test_rr_op!(19, sll, 0x90909080, 0x21212121, 0xffffffc7);
+    test_rr_op!(20, sll, 0x48484000, 0x21212121, 0xffffffce);
+    test_rr_op!(21, sll, 0x80000000, 0x21212121, 0xffffffff);
+
+    test_rr_src1_eq_dest!(22, sll, 0x00000080, 0x00000001, 7);
+    test_rr_src2_eq_dest!(23, sll, 0x00004000, 0x00000001, 14);
+    test_rr_src12_eq_dest!(24, sll, 24, 3);
+
+    test_rr_zerosrc1!(40, sll, 0, 15);
+    test_rr_zerosrc2!(41, sll, 32, 32);
+    test_rr_zerosrc12!(42, sll, 0);
+    test_rr_zerodest!(43, sll, 1024, 2048);
+}
+
+#[test]
+fn slt() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/slt.S
+    test_rr_op!(2, slt, 0, 0x00000000, 0x00000000);
+    test_rr_op!(3, slt, 0, 0x00000001, 0x00000001);
+    test_rr_op!(4, slt, 1, 0x00000003, 0x00000007);
+    test_rr_op!(5, slt, 0, 0x00000007, 0x00000003);
+    test_rr_op!(6, slt, 0, 0x00000000, 0xffff8000);
+    test_rr_op!(7, slt, 1, 0x80000000, 0x00000000);
+    test_rr_op!(8, slt, 1, 0x80000000, 0xffff8000);
+    test_rr_op!(9, slt, 1, 0x00000000, 0x00007fff);
+    test_rr_op!(10, slt, 0, 0x7fffffff, 0x00000000);
+    test_rr_op!(11, slt, 0, 0x7fffffff, 0x00007fff);
+    test_rr_op!(12, slt, 1, 0x80000000, 0x00007fff);
+    test_rr_op!(13, slt, 0, 0x7fffffff, 0xffff8000);
+    test_rr_op!(14, slt, 0, 0x00000000, 0xffffffff);
+    test_rr_op!(15, slt, 1, 0xffffffff, 0x00000001);
+    test_rr_op!(16, slt, 0, 0xffffffff, 0xffffffff);
+
+    test_rr_src1_eq_dest!(17, slt, 0, 14, 13);
+    test_rr_src2_eq_dest!(18, slt, 1, 11, 13);
+    test_rr_src12_eq_dest!(19, slt, 0, 13);
+
+    test_rr_zerosrc1!(35, slt, 0, 0xffffffff);
+    test_rr_zerosrc2!(36, slt, 1, 0xffffffff);
+    test_rr_zerosrc12!(37, slt, 0);
+    test_rr_zerodest!(38, slt, 16, 30);
+}
+
+#[test]
+fn sltu() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/sltu.S
+    test_rr_op!(2, sltu, 0, 0x00000000, 0x00000000);
+    test_rr_op!(3, sltu, 0, 0x00000001, 0x00000001);
+    test_rr_op!(4, sltu, 1, 0x00000003, 0x00000007);
+    test_rr_op!(5, sltu, 0, 0x00000007, 0x00000003);
+    test_rr_op!(6, sltu, 1, 0x00000000, 0xffff8000);
+    test_rr_op!(7, sltu, 0, 0x80000000, 0x00000000);
+    test_rr_op!(8, sltu, 1, 0x80000000, 0xffff8000);
+    test_rr_op!(9, sltu, 1, 0x00000000, 0x00007fff);
+    test_rr_op!(10, sltu, 0, 0x7fffffff, 0x00000000);
+    test_rr_op!(11, sltu, 0, 0x7fffffff, 0x00007fff);
+    test_rr_op!(12, sltu, 0, 0x80000000, 0x00007fff);
+    test_rr_op!(13, sltu, 1, 0x7fffffff, 0xffff8000);
+    test_rr_op!(14, sltu, 1, 0x00000000, 0xffffffff);
+    test_rr_op!(15, sltu, 0, 0xffffffff, 0x00000001);
+    test_rr_op!(16, sltu, 0, 0xffffffff, 0xffffffff);
+
+    test_rr_src1_eq_dest!(17, sltu, 0, 14, 13);
+    test_rr_src2_eq_dest!(18, sltu, 1, 11, 13);
+    test_rr_src12_eq_dest!(19, sltu, 0, 13);
+
+    test_rr_zerosrc1!(35, sltu, 1, 0xffffffff);
+    test_rr_zerosrc2!(36, sltu, 0, 0xffffffff);
+    test_rr_zerosrc12!(37, sltu, 0);
+    test_rr_zerodest!(38, sltu, 16, 30);
+}
+
+#[test]
+fn xor() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/xor.S
+    test_rr_op!(2, xor, 0xf00ff00f, 0xff00ff00, 0x0f0f0f0f);
+    test_rr_op!(3, xor, 0xff00ff00, 0x0ff00ff0, 0xf0f0f0f0);
+    test_rr_op!(4, xor, 0x0ff00ff0, 0x00ff00ff, 0x0f0f0f0f);
+    test_rr_op!(5, xor, 0x00ff00ff, 0xf00ff00f, 0xf0f0f0f0);
+
+    test_rr_src1_eq_dest!(6, xor, 0xf00ff00f, 0xff00ff00, 0x0f0f0f0f);
+    test_rr_src2_eq_dest!(7, xor, 0xf00ff00f, 0xff00ff00, 0x0f0f0f0f);
+    test_rr_src12_eq_dest!(8, xor, 0x00000000, 0xff00ff00);
+
+    test_rr_zerosrc1!(24, xor, 0xff00ff00, 0xff00ff00);
+    test_rr_zerosrc2!(25, xor, 0x00ff00ff, 0x00ff00ff);
+    test_rr_zerosrc12!(26, xor, 0);
+    test_rr_zerodest!(27, xor, 0x11111111, 0x22222222);
+}
+
+#[test]
+fn srl() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/srl.S
+    test_rr_op!(2, srl, 0x80000000, 0x80000000, 0);
+    test_rr_op!(3, srl, 0x40000000, 0x80000000, 1);
+    test_rr_op!(4, srl, 0x01000000, 0x80000000, 7);
+    test_rr_op!(5, srl, 0x00020000, 0x80000000, 14);
+    test_rr_op!(6, srl, 0x00000001, 0x80000000, 31);
+    test_rr_op!(7, srl, 0xffffffff, 0xffffffff, 0);
+    test_rr_op!(8, srl, 0x7fffffff, 0xffffffff, 1);
+    test_rr_op!(9, srl, 0x01ffffff, 0xffffffff, 7);
+    test_rr_op!(10, srl, 0x0003ffff, 0xffffffff, 14);
+    test_rr_op!(11, srl, 0x00000001, 0xffffffff, 31);
+    test_rr_op!(12, srl, 0x21212121, 0x21212121, 0);
+    test_rr_op!(13, srl, 0x10909090, 0x21212121, 1);
+    test_rr_op!(14, srl, 0x00424242, 0x21212121, 7);
+    test_rr_op!(15, srl, 0x00008484, 0x21212121, 14);
+    test_rr_op!(16, srl, 0x00000000, 0x21212121, 31);
+    test_rr_op!(17, srl, 0x21212121, 0x21212121, 0xffffffc0);
+    test_rr_op!(18, srl, 0x10909090, 0x21212121, 0xffffffc1);
+    test_rr_op!(19, srl, 0x00424242, 0x21212121, 0xffffffc7);
+    test_rr_op!(20, srl, 0x00008484, 0x21212121, 0xffffffce);
+    test_rr_op!(21, srl, 0x00000000, 0x21212121, 0xffffffff);
+
+    test_rr_src1_eq_dest!(22, srl, 0x01000000, 0x80000000, 7);
+    test_rr_src2_eq_dest!(23, srl, 0x00020000, 0x80000000, 14);
+    test_rr_src12_eq_dest!(24, srl, 0, 7);
+
+    test_rr_zerosrc1!(40, srl, 0, 15);
+    test_rr_zerosrc2!(41, srl, 32, 32);
+    test_rr_zerosrc12!(42, srl, 0);
+    test_rr_zerodest!(43, srl, 1024, 2048);
+}
+
+#[test]
+fn sra() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/sra.S
+    test_rr_op!(2, sra, 0x80000000, 0x80000000, 0);
+    test_rr_op!(3, sra, 0xc0000000, 0x80000000, 1);
+    test_rr_op!(4, sra, 0xff000000, 0x80000000, 7);
+    test_rr_op!(5, sra, 0xfffe0000, 0x80000000, 14);
+    test_rr_op!(6, sra, 0xffffffff, 0x80000000, 31);
+    test_rr_op!(7, sra, 0x7fffffff, 0x7fffffff, 0);
+    test_rr_op!(8, sra, 0x3fffffff, 0x7fffffff, 1);
+    test_rr_op!(9, sra, 0x00ffffff, 0x7fffffff, 7);
+    test_rr_op!(10, sra, 0x0001ffff, 0x7fffffff, 14);
+    test_rr_op!(11, sra, 0x00000000, 0x7fffffff, 31);
+    test_rr_op!(12, sra, 0x81818181, 0x81818181, 0);
+    test_rr_op!(13, sra, 0xc0c0c0c0, 0x81818181, 1);
+    test_rr_op!(14, sra, 0xff030303, 0x81818181, 7);
+    test_rr_op!(15, sra, 0xfffe0606, 0x81818181, 14);
+    test_rr_op!(16, sra, 0xffffffff, 0x81818181, 31);
+    test_rr_op!(17, sra, 0x81818181, 0x81818181, 0xffffffc0);
+    test_rr_op!(18, sra, 0xc0c0c0c0, 0x81818181, 0xffffffc1);
+    test_rr_op!(19, sra, 0xff030303, 0x81818181, 0xffffffc7);
+    test_rr_op!(20, sra, 0xfffe0606, 0x81818181, 0xffffffce);
+    test_rr_op!(21, sra, 0xffffffff, 0x81818181, 0xffffffff);
+
+    test_rr_src1_eq_dest!(22, sra, 0xff000000, 0x80000000, 7);
+    test_rr_src2_eq_dest!(23, sra, 0xfffe0000, 0x80000000, 14);
+    test_rr_src12_eq_dest!(24, sra, 0, 7);
+
+    test_rr_zerosrc1!(40, sra, 0, 15);
+    test_rr_zerosrc2!(41, sra, 32, 32);
+    test_rr_zerosrc12!(42, sra, 0);
+    test_rr_zerodest!(43, sra, 1024, 2048);
+}
+
+#[test]
+fn or() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/or.S
+    test_rr_op!(2, or, 0xff0fff0f, 0xff00ff00, 0x0f0f0f0f);
+    test_rr_op!(3, or, 0xfff0fff0, 0x0ff00ff0, 0xf0f0f0f0);
+    test_rr_op!(4, or, 0x0fff0fff, 0x00ff00ff, 0x0f0f0f0f);
+    test_rr_op!(5, or, 0xf0fff0ff, 0xf00ff00f, 0xf0f0f0f0);
+
+    test_rr_src1_eq_dest!(6, or, 0xff0fff0f, 0xff00ff00, 0x0f0f0f0f);
+    test_rr_src2_eq_dest!(7, or, 0xff0fff0f, 0xff00ff00, 0x0f0f0f0f);
+    test_rr_src12_eq_dest!(8, or, 0xff00ff00, 0xff00ff00);
+
+    test_rr_zerosrc1!(24, or, 0xff00ff00, 0xff00ff00);
+    test_rr_zerosrc2!(25, or, 0x00ff00ff, 0x00ff00ff);
+    test_rr_zerosrc12!(26, or, 0);
+    test_rr_zerodest!(27, or, 0x11111111, 0x22222222);
+}
+
+#[test]
+fn and() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/and.S
+    test_rr_op!(2, and, 0x0f000f00, 0xff00ff00, 0x0f0f0f0f);
+    test_rr_op!(3, and, 0x00f000f0, 0x0ff00ff0, 0xf0f0f0f0);
+    test_rr_op!(4, and, 0x000f000f, 0x00ff00ff, 0x0f0f0f0f);
+    test_rr_op!(5, and, 0xf000f000, 0xf00ff00f, 0xf0f0f0f0);
+
+    test_rr_src1_eq_dest!(6, and, 0x0f000f00, 0xff00ff00, 0x0f0f0f0f);
+    test_rr_src2_eq_dest!(7, and, 0x00f000f0, 0x0ff00ff0, 0xf0f0f0f0);
+    test_rr_src12_eq_dest!(8, and, 0xff00ff00, 0xff00ff00);
+
+    test_rr_zerosrc1!(24, and, 0, 0xff00ff00);
+    test_rr_zerosrc2!(25, and, 0, 0x00ff00ff);
+    test_rr_zerosrc12!(26, and, 0);
+    test_rr_zerodest!(27, and, 0x11111111, 0x22222222);
+}
+
+#[test]
+fn beq() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/beq.S
+    test_br_op!(2, beq, true, 0x00000000, 0x00000000);
+    test_br_op!(3, beq, true, 0x00000001, 0x00000001);
+    test_br_op!(4, beq, true, 0xffffffff, 0xffffffff);
+
+    test_br_op!(5, beq, false, 0x00000000, 0x00000001);
+    test_br_op!(6, beq, false, 0x00000001, 0x00000000);
+    test_br_op!(7, beq, false, 0xffffffff, 0x00000001);
+    test_br_op!(8, beq, false, 0x00000001, 0xffffffff);
+}
+
+#[test]
+fn bne() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/bne.S
+    test_br_op!(2, bne, true, 0x00000000, 0x00000001);
+    test_br_op!(3, bne, true, 0x00000001, 0x00000000);
+    test_br_op!(4, bne, true, 0xffffffff, 0x00000001);
+    test_br_op!(5, bne, true, 0x00000001, 0xffffffff);
+
+    test_br_op!(6, bne, false, 0x00000000, 0x00000000);
+    test_br_op!(7, bne, false, 0x00000001, 0x00000001);
+    test_br_op!(8, bne, false, 0xffffffff, 0xffffffff);
+}
+
+#[test]
+fn blt() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/blt.S
+    test_br_op!(2, blt, true, 0x00000000, 0x00000001);
+    test_br_op!(3, blt, true, 0xffffffff, 0x00000001);
+    test_br_op!(4, blt, true, 0xfffffffe, 0xffffffff);
+
+    test_br_op!(5, blt, false, 0x00000001, 0x00000000);
+    test_br_op!(6, blt, false, 0x00000001, 0xffffffff);
+    test_br_op!(7, blt, false, 0xffffffff, 0xfffffffe);
+    test_br_op!(8, blt, false, 0x00000001, 0xfffffffe);
+
+    // Signed, so 0x80000000 is the smallest value.
+    test_br_op!(9, blt, true, 0x80000000, 0x00000001);
+}
+
+#[test]
+fn bge() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/bge.S
+    test_br_op!(2, bge, true, 0x00000000, 0x00000000);
+    test_br_op!(3, bge, true, 0x00000001, 0x00000001);
+    test_br_op!(4, bge, true, 0xffffffff, 0xffffffff);
+    test_br_op!(5, bge, true, 0x00000001, 0x00000000);
+    test_br_op!(6, bge, true, 0x00000001, 0xffffffff);
+    test_br_op!(7, bge, true, 0xffffffff, 0xfffffffe);
+
+    test_br_op!(8, bge, false, 0x00000000, 0x00000001);
+    test_br_op!(9, bge, false, 0xffffffff, 0x00000001);
+    test_br_op!(10, bge, false, 0xfffffffe, 0xffffffff);
+    test_br_op!(11, bge, false, 0xfffffffe, 0x00000001);
+}
+
+#[test]
+fn bltu() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/bltu.S
+    test_br_op!(2, bltu, true, 0x00000000, 0x00000001);
+    test_br_op!(3, bltu, true, 0xfffffffe, 0xffffffff);
+    test_br_op!(4, bltu, true, 0x00000000, 0xffffffff);
+
+    test_br_op!(5, bltu, false, 0x00000001, 0x00000000);
+    test_br_op!(6, bltu, false, 0xffffffff, 0xfffffffe);
+    test_br_op!(7, bltu, false, 0xffffffff, 0x00000000);
+    test_br_op!(8, bltu, false, 0x80000000, 0x7fffffff);
+
+    // Unsigned, so 0x80000000 is not less than 1.
+    test_br_op!(9, bltu, false, 0x80000000, 0x00000001);
+}
+
+#[test]
+fn bgeu() {
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64ui/bgeu.S
+    test_br_op!(2, bgeu, true, 0x00000000, 0x00000000);
+    test_br_op!(3, bgeu, true, 0x00000001, 0x00000001);
+    test_br_op!(4, bgeu, true, 0xffffffff, 0xffffffff);
+    test_br_op!(5, bgeu, true, 0x00000001, 0x00000000);
+    test_br_op!(6, bgeu, true, 0xffffffff, 0xfffffffe);
+    test_br_op!(7, bgeu, true, 0xffffffff, 0x00000000);
+
+    test_br_op!(8, bgeu, false, 0x00000000, 0x00000001);
+    test_br_op!(9, bgeu, false, 0xfffffffe, 0xffffffff);
+    test_br_op!(10, bgeu, false, 0x00000000, 0xffffffff);
+    test_br_op!(11, bgeu, false, 0x7fffffff, 0x80000000);
+}
+
+#[test]
+fn branch_backward() {
+    let mut cpu = Processor::new();
+    cpu.set_pc(0x1000);
+    cpu.beq(0, 0, -8i32 as u32);
+    assert_eq!(0x0ff8, cpu.pc());
+}
+
+#[test]
+fn branch_to_self() {
+    let mut cpu = Processor::new();
+    cpu.set_pc(0x1000);
+    assert!(cpu.beq(0, 0, 0));
+    assert_eq!(0x1000, cpu.pc());
+    assert!(!cpu.bne(0, 0, 0));
+    assert_eq!(0x1000, cpu.pc());
+    assert!(cpu.jal(0, 0));
+    assert_eq!(0x1000, cpu.pc());
+}
+
+#[test]
+fn pc_only_moves_on_control_flow() {
+    let mut cpu = Processor::new();
+    cpu.set_pc(0x1000);
+    cpu.addi(1, 0, 1);
+    cpu.add(2, 1, 1);
+    cpu.bne(0, 0, 0x10);
+    assert_eq!(0x1000, cpu.pc());
+}
+
+#[test]
+fn jal() {
+    let mut cpu = Processor::new();
+    cpu.set_pc(0x1000);
+    cpu.jal(1, 0x20);
+    assert_eq!(0x1004, cpu.get(1));
+    assert_eq!(0x1020, cpu.pc());
+
+    cpu.jal(0, -0x100i32 as u32);
+    assert_eq!(0, cpu.get(0));
+    assert_eq!(0x0f20, cpu.pc());
+}
+
+#[test]
+fn jalr() {
+    let mut cpu = Processor::new();
+    cpu.set_pc(0x1000);
+    cpu.set(2, 0x2001);
+    cpu.jalr(1, 2, 0x10);
+    assert_eq!(0x1004, cpu.get(1));
+    assert_eq!(0x2010, cpu.pc());
+
+    // rd == rs1 must use the old value of rs1 for the target.
+    cpu.set(3, 0x3000);
+    cpu.jalr(3, 3, sign_extend(0xffc));
+    assert_eq!(0x2014, cpu.get(3));
+    assert_eq!(0x2ffc, cpu.pc());
+}
+
+#[test]
+fn ret() {
+    let mut cpu = Processor::with_poisoned_registers(0x12345600);
+    let before: Vec<u32> = (0..32).map(|reg| cpu.get(reg)).collect();
+    cpu.set_pc(0x2000);
+    cpu.jalr(0, 1, 0);
+    assert_eq!(0x12345600, cpu.pc());  // x1 == 0x12345601 with bit 0 cleared.
+    let after: Vec<u32> = (0..32).map(|reg| cpu.get(reg)).collect();
+    assert_eq!(before, after);
+}
+
+#[test]
+fn shnadd() {
+    test_rr_op!(2, sh1add, 0x00000003, 0x00000001, 0x00000001);
+    test_rr_op!(3, sh2add, 0x00000014, 0x00000004, 0x00000004);
+    test_rr_op!(4, sh3add, 0x00000107, 0x00000020, 0x00000007);
+
+    test_rr_op!(5, sh1add, 0xfffffffe, 0xffffffff, 0x00000000);
+    test_rr_op!(6, sh2add, 0x00000000, 0x40000000, 0x00000000);
+    test_rr_op!(7, sh3add, 0x00000000, 0x20000000, 0x00000000);
+
+    // Overflow wraps like ADD.
+    test_rr_op!(8, sh1add, 0x00000001, 0x80000001, 0xffffffff);
+    test_rr_op!(9, sh3add, 0x00000004, 0xffffffff, 0x0000000c);
+}
+
+#[test]
+fn zbb_logical() {
+    test_rr_op!(2, andn, 0xf000f000, 0xff00ff00, 0x0ff00ff0);
+    test_rr_op!(3, andn, 0x00000000, 0x12345678, 0xffffffff);
+    test_rr_op!(4, orn, 0xfff0fff0, 0xff00ff00, 0x000f000f);
+    test_rr_op!(5, orn, 0xffffffff, 0x00000000, 0x00000000);
+    test_rr_op!(6, xnor, 0xffffffff, 0x12345678, 0x12345678);
+    test_rr_op!(7, xnor, 0x0f0f0f0f, 0xff00ff00, 0x0ff00ff0);
+}
+
+#[test]
+fn zbb_count() {
+    test_r_op!(2, clz, 32, 0x00000000);
+    test_r_op!(3, clz, 0, 0x80000000);
+    test_r_op!(4, clz, 31, 0x00000001);
+    test_r_op!(5, clz, 12, 0x000fffff);
+
+    test_r_op!(6, ctz, 32, 0x00000000);
+    test_r_op!(7, ctz, 0, 0x00000001);
+    test_r_op!(8, ctz, 31, 0x80000000);
+
+    test_r_op!(9, cpop, 0, 0x00000000);
+    test_r_op!(10, cpop, 32, 0xffffffff);
+    test_r_op!(11, cpop, 16, 0xf0f0f0f0);
+    test_r_op!(12, cpop, 13, 0x12345678);
+}
+
+#[test]
+fn zbb_min_max() {
+    test_rr_op!(2, min, 0x80000000, 0x80000000, 0x7fffffff);
+    test_rr_op!(3, min, 0xffffffff, 0xffffffff, 0x00000000);
+    test_rr_op!(4, max, 0x7fffffff, 0x80000000, 0x7fffffff);
+    test_rr_op!(5, max, 0x00000000, 0xffffffff, 0x00000000);
+
+    test_rr_op!(6, minu, 0x7fffffff, 0x80000000, 0x7fffffff);
+    test_rr_op!(7, minu, 0x00000000, 0xffffffff, 0x00000000);
+    test_rr_op!(8, maxu, 0x80000000, 0x80000000, 0x7fffffff);
+    test_rr_op!(9, maxu, 0xffffffff, 0xffffffff, 0x00000000);
+}
+
+#[test]
+fn zbb_extend() {
+    test_r_op!(2, sext_b, 0x0000007f, 0xffffff7f);
+    test_r_op!(3, sext_b, 0xffffff80, 0x00000080);
+    test_r_op!(4, sext_h, 0x00007fff, 0xffff7fff);
+    test_r_op!(5, sext_h, 0xffff8000, 0x00008000);
+    test_r_op!(6, zext_h, 0x00008000, 0xffff8000);
+    test_r_op!(7, zext_h, 0x00000000, 0xffff0000);
+}
+
+#[test]
+fn zbb_rotate() {
+    test_rr_op!(2, rol, 0x00000003, 0x80000001, 1);
+    test_rr_op!(3, rol, 0x80000001, 0x80000001, 32);
+    test_rr_op!(4, ror, 0xc0000000, 0x80000001, 1);
+    test_rr_op!(5, ror, 0x81234567, 0x12345678, 0xffffffe4);
+
+    test_imm_op!(6, rori, 0xc0000000, 0x80000001, 1);
+    test_imm_op!(7, rori, 0x81234567, 0x12345678, 4);
+}
+
+#[test]
+fn zbb_bytes() {
+    test_r_op!(2, rev8, 0x78563412, 0x12345678);
+    test_r_op!(3, rev8, 0x000000ff, 0xff000000);
+
+    test_r_op!(4, orc_b, 0x00000000, 0x00000000);
+    test_r_op!(5, orc_b, 0xff00ffff, 0x80000101);
+}
+
+#[test]
+fn fclass_s() {
+    let classify = |bits: u32| {
+        let mut cpu = Processor::new();
+        cpu.set_float(1, f32::from_bits(bits));
+        cpu.fclass_s(3, 1);
+        cpu.get(3)
+    };
+
+    // From https://github.com/riscv/riscv-tests/blob/master/isa/rv64uf/fclass.S
+    assert_eq!(1 << 0, classify(0xff800000));
+    assert_eq!(1 << 1, classify(0xbf800000));
+    assert_eq!(1 << 2, classify(0x807fffff));
+    assert_eq!(1 << 3, classify(0x80000000));
+    assert_eq!(1 << 4, classify(0x00000000));
+    assert_eq!(1 << 5, classify(0x007fffff));
+    assert_eq!(1 << 6, classify(0x3f800000));
+    assert_eq!(1 << 7, classify(0x7f800000));
+    assert_eq!(1 << 8, classify(0x7f800001));
+    assert_eq!(1 << 9, classify(0x7fc00000));
+}
+
+#[test]
+fn fcvt_w_s() {
+    test_fp_int_op!(2, fcvt_w_s, 0x01, 0xffffffff, -1.1);
+    test_fp_int_op!(3, fcvt_w_s, 0x00, 0xffffffff, -1.0);
+    test_fp_int_op!(4, fcvt_w_s, 0x01, 0x00000001, 0.9);
+    test_fp_int_op!(5, fcvt_w_s, 0x01, 0x00000002, 1.5);
+    test_fp_int_op!(6, fcvt_w_s, 0x01, 0x00000002, 2.5);
+    test_fp_int_op!(7, fcvt_w_s, 0x00, 0x80000000, -2147483648.0);
+
+    test_fp_int_op!(8, fcvt_w_s, 0x10, 0x80000000, -3e9);
+    test_fp_int_op!(9, fcvt_w_s, 0x10, 0x7fffffff, 3e9);
+    test_fp_int_op!(10, fcvt_w_s, 0x10, 0x7fffffff, f32::NAN);
+    test_fp_int_op!(11, fcvt_w_s, 0x10, 0x7fffffff, f32::INFINITY);
+    test_fp_int_op!(12, fcvt_w_s, 0x10, 0x80000000, f32::NEG_INFINITY);
+}
+
+#[test]
+fn fcvt_wu_s() {
+    test_fp_int_op!(2, fcvt_wu_s, 0x10, 0x00000000, -3.0);
+    test_fp_int_op!(3, fcvt_wu_s, 0x01, 0x00000000, -0.4);
+    test_fp_int_op!(4, fcvt_wu_s, 0x01, 0x00000002, 1.5);
+    test_fp_int_op!(5, fcvt_wu_s, 0x00, 3000000000, 3e9);
+
+    test_fp_int_op!(6, fcvt_wu_s, 0x10, 0xffffffff, 5e9);
+    test_fp_int_op!(7, fcvt_wu_s, 0x10, 0xffffffff, f32::NAN);
+    test_fp_int_op!(8, fcvt_wu_s, 0x10, 0xffffffff, f32::INFINITY);
+    test_fp_int_op!(9, fcvt_wu_s, 0x10, 0x00000000, f32::NEG_INFINITY);
+}
+
+#[test]
+fn fcvt_s_w() {
+    test_int_fp_op!(2, fcvt_s_w, 0x00, 2.0, 0x00000002);
+    test_int_fp_op!(3, fcvt_s_w, 0x00, -2.0, 0xfffffffe);
+    test_int_fp_op!(4, fcvt_s_w, 0x00, -2147483648.0, 0x80000000);
+    test_int_fp_op!(5, fcvt_s_w, 0x01, 2147483648.0, 0x7fffffff);
+
+    test_int_fp_op!(6, fcvt_s_wu, 0x00, 2.0, 0x00000002);
+    test_int_fp_op!(7, fcvt_s_wu, 0x00, 2147483648.0, 0x80000000);
+    test_int_fp_op!(8, fcvt_s_wu, 0x01, 4294967296.0, 0xffffffff);
+}
+
+#[test]
+fn fcvt_round_trip() {
+    let mut cpu = Processor::new();
+    cpu.set_signed(1, -12345);
+    cpu.fcvt_s_w(2, 1);
+    cpu.fcvt_w_s(3, 2);
+    assert_eq!(-12345, cpu.get_signed(3));
+    assert_eq!(0, cpu.fflags());
+}
+
+#[test]
+fn assert_state_matches() {
+    let mut cpu = Processor::new();
+    cpu.set(1, 5);
+    cpu.set(3, 7);
+    assert_state!(cpu, 0 => 0, 1 => 5, 3 => 7);
+}
+
+#[test]
+#[should_panic(expected = "register state mismatch:\n\
+                           x1: expected 0x00000006, found 0x00000005\n\
+                           x3: expected 0x00000008, found 0x00000007")]
+fn assert_state_mismatch() {
+    let mut cpu = Processor::new();
+    cpu.set(1, 5);
+    cpu.set(3, 7);
+    assert_state!(cpu, 1 => 6, 2 => 0, 3 => 8);
+}
+
+#[test]
+fn assert_state_pc_matches() {
+    let mut cpu = Processor::new();
+    cpu.set_pc(0x1000);
+    cpu.jal(1, 0x10);
+    assert_state!(cpu, pc => 0x1010);
+    assert_state!(cpu, pc => 0x1010, 1 => 0x1004,);
+}
+
+#[test]
+#[should_panic(expected = "register state mismatch:\n\
+                           pc: expected 0x00001004, found 0x00001010\n\
+                           x1: expected 0x00001000, found 0x00001004")]
+fn assert_state_pc_mismatch() {
+    let mut cpu = Processor::new();
+    cpu.set_pc(0x1000);
+    cpu.jal(1, 0x10);
+    assert_state!(cpu, pc => 0x1004, 1 => 0x1000);
+}
+
+#[test]
+fn processor_is_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<Processor>();
+}
+
+#[test]
+fn arith_overflow_hook() {
+    use std::sync::{Arc, Mutex};
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut cpu = Processor::new();
+    let recorded = events.clone();
+    cpu.set_arith_overflow_hook(move |event| recorded.lock().unwrap().push(*event));
+    cpu.set_pc(0x100);
+
+    cpu.set(1, 0x7fffffff);
+    cpu.addi(2, 1, 0x001);
+    cpu.addi(3, 1, sign_extend(0xfff));
+    assert_eq!(0x80000000, cpu.get(2));
+    assert_eq!(vec![ArithOverflow {
+                         pc: 0x100,
+                         op: ArithOp::Addi,
+                         lhs: 0x7fffffff,
+                         rhs: 0x001,
+                     }],
+               *events.lock().unwrap());
+
+    events.lock().unwrap().clear();
+    cpu.set(4, 0xffffffff);
+    cpu.add(5, 4, 4);
+    cpu.sub(5, 2, 1);
+    cpu.jal(0, -0x200i32 as u32);
+    let ops: Vec<ArithOp> = events.lock().unwrap().iter().map(|e| e.op).collect();
+    assert_eq!(vec![ArithOp::Sub, ArithOp::Jump], ops);
+}
+
+#[test]
+fn poisoned_registers() {
+    let mut cpu = Processor::with_poisoned_registers(0xdeadbeef);
+    assert_eq!(0, cpu.get(0));
+    assert_eq!(0xdeadbee1, cpu.get(1));
+    assert_eq!(0xdeadbee5, cpu.get(5));
+    assert_eq!(0xdeadbeff, cpu.get(31));
+
+    cpu.addi(5, 0, 42);
+    assert_eq!(42, cpu.get(5));
+
+    let cpu = Processor::with_poisoned_registers(0);
+    assert_eq!(5, cpu.get(5));
+}
+
+#[test]
+fn instruction_lengths() {
+    assert_eq!(2, instruction_length(0x0001));  // c.nop
+    assert_eq!(2, instruction_length(0x9002));  // c.ebreak
+    assert_eq!(2, instruction_length(0x0000));
+    assert_eq!(4, instruction_length(0x0093));  // addi x1, x0, ...
+    assert_eq!(4, instruction_length(0x1073));  // csrrw
+    assert_eq!(6, instruction_length(0x001f));
+    assert_eq!(8, instruction_length(0x003f));
+    assert_eq!(10, instruction_length(0x007f));
+    assert_eq!(22, instruction_length(0x607f));
+}
+
+#[test]
+fn registers_by_name() {
+    assert_eq!(Some(0), register_by_name("zero"));
+    assert_eq!(Some(0), register_by_name("x0"));
+    assert_eq!(Some(8), register_by_name("fp"));
+    assert_eq!(Some(8), register_by_name("s0"));
+    assert_eq!(Some(27), register_by_name("s11"));
+    assert_eq!(Some(31), register_by_name("x31"));
+    assert_eq!(None, register_by_name("x32"));
+    assert_eq!(None, register_by_name("x01"));
+    assert_eq!(None, register_by_name("x"));
+    assert_eq!(None, register_by_name("x+1"));
+    assert_eq!(None, register_by_name("a8"));
+}
+
+#[test]
+fn set_registers() {
+    let mut cpu = Processor::new();
+    cpu.set_registers(&[("a0", 1), ("a1", 2), ("sp", 0x8000), ("zero", 5)])
+        .unwrap();
+    assert_state!(cpu, 10 => 1, 11 => 2, 2 => 0x8000, 0 => 0);
+
+    assert_eq!(Err("unknown register name: q0".to_string()),
+               cpu.set_registers(&[("a2", 3), ("q0", 4)]));
+    assert_eq!(0, cpu.get(12));
+}
+
+#[test]
+fn abi_names() {
+    assert_eq!(Some("zero"), abi_name(0));
+    assert_eq!(Some("ra"), abi_name(1));
+    assert_eq!(Some("gp"), abi_name(3));
+    assert_eq!(Some("tp"), abi_name(4));
+    assert_eq!(Some("s0"), abi_name(8));
+    assert_eq!(Some("t6"), abi_name(31));
+    assert_eq!(None, abi_name(32));
+    for reg in 0..32 {
+        assert_eq!(Some(reg), abi_name(reg).and_then(register_by_name));
+    }
+}